@@ -0,0 +1,493 @@
+use crate::{pos_fields_from_details, MergedToken};
+use aho_corasick::{AhoCorasickBuilder, MatchKind};
+use anyhow::{Context, Result};
+use csv::ReaderBuilder;
+use lindera_tokenizer::token::Token;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use unicode_normalization::UnicodeNormalization;
+
+fn default_true() -> bool {
+    true
+}
+
+/// ユーザー辞書のCSVレコードを表す構造体。
+/// `UserDictionary` 列以外は省略可能で、HuggingFaceトークナイザーの
+/// `AddedToken` にならったマッチ挙動のフラグを持つ。
+#[derive(Debug, Deserialize)]
+struct UserDictionaryRecord {
+    #[serde(rename = "UserDictionary")]
+    user_dictionary: String,
+    /// トークン境界に揃った場合のみマッチさせるか（falseならより大きな連続の内部でもマッチを許す）
+    #[serde(rename = "SingleWord", default = "default_true")]
+    single_word: bool,
+    /// マッチの前方にある空白を結合語に吸収するか
+    #[serde(rename = "LStrip", default)]
+    lstrip: bool,
+    /// マッチの後方にある空白を結合語に吸収するか
+    #[serde(rename = "RStrip", default)]
+    rstrip: bool,
+    /// 比較前に表層形・入力テキストの双方をNFKC正規化（全角/半角統一を含む）するか
+    #[serde(rename = "Normalized", default)]
+    normalized: bool,
+}
+
+/// ユーザー辞書の1エントリとそのマッチ挙動
+pub(crate) struct UserDictionaryEntry {
+    pub(crate) surface: String,
+    pub(crate) single_word: bool,
+    pub(crate) lstrip: bool,
+    pub(crate) rstrip: bool,
+    pub(crate) normalized: bool,
+}
+
+/// ユーザー辞書によって結合されたトークンに付与する合成品詞ラベル
+const CUSTOM_DICTIONARY_POS: &str = "カスタム";
+const CUSTOM_DICTIONARY_POS_DETAIL: &str = "固有名詞";
+
+/// ユーザー辞書リストをCSVから読み込む関数
+pub(crate) fn load_user_dictionary(path: &str) -> Result<Vec<UserDictionaryEntry>> {
+    let file = File::open(path).with_context(|| {
+        format!(
+            "ユーザー辞書ファイル '{}' を開くことができませんでした。",
+            path
+        )
+    })?;
+    let mut rdr = ReaderBuilder::new().has_headers(true).from_reader(file);
+
+    let mut entries = Vec::new();
+    for result in rdr.deserialize() {
+        let record: UserDictionaryRecord =
+            result.with_context(|| "ユーザー辞書のレコードのデシリアライズに失敗しました。")?;
+        entries.push(UserDictionaryEntry {
+            surface: record.user_dictionary,
+            single_word: record.single_word,
+            lstrip: record.lstrip,
+            rstrip: record.rstrip,
+            normalized: record.normalized,
+        });
+    }
+    Ok(entries)
+}
+
+/// LinderaのTokenをそのままMergedTokenに変換する関数
+fn token_to_merged(token: &Token) -> MergedToken {
+    let (pos, pos_detail, base_form, reading) = pos_fields_from_details(&token.details);
+    MergedToken {
+        text: token.text.to_string(),
+        byte_start: token.byte_start,
+        byte_end: token.byte_end,
+        position: token.position,
+        position_length: token.position_length,
+        pos,
+        pos_detail,
+        base_form,
+        reading,
+    }
+}
+
+/// テキスト全体を1回でNFKC正規化し、正規化後のバイト位置→元テキストのバイト位置の
+/// 対応表を作る。`offsets[i]` は正規化後テキストのバイトオフセット `i` に対応する
+/// 元テキストのバイトオフセット。末尾に元テキストの全長を番兵として追加し、
+/// マッチ終端が文字列末尾の場合も解決できるようにする。
+///
+/// NFKCは半角カタカナ+濁点（"ｶ"+"ﾞ"）のように隣接する文字を1文字（"ガ"）へ
+/// 合成することがあるため、1文字ずつ正規化すると合成が起こらず結果がずれる。
+/// そのため正規化はテキスト全体に対して一度だけ行い、元の文字列側を先頭から
+/// 少しずつ伸ばしながら同じ正規化結果になる最小の区間を探すことで、
+/// 正規化後の各バイトがどの元テキスト区間に由来するかを割り出す。
+fn normalize_with_offsets(text: &str) -> (String, Vec<usize>) {
+    let normalized: String = text.nfkc().collect();
+    let orig_chars: Vec<(usize, char)> = text.char_indices().collect();
+
+    let mut offsets = Vec::with_capacity(normalized.len() + 1);
+    let mut orig_idx = 0usize;
+    let mut norm_consumed = 0usize;
+
+    while orig_idx < orig_chars.len() {
+        let mut window_end = orig_idx + 1;
+        let chunk_normalized = loop {
+            let window: String = orig_chars[orig_idx..window_end].iter().map(|&(_, c)| c).collect();
+            let chunk: String = window.nfkc().collect();
+            let remaining = &normalized[norm_consumed..];
+            if !chunk.is_empty() && remaining.starts_with(&chunk) {
+                break chunk;
+            }
+            if window_end >= orig_chars.len() {
+                // 最後まで伸ばしても一致しない場合は、現時点の結果をそのまま採用する
+                break chunk;
+            }
+            window_end += 1;
+        };
+
+        let orig_byte_start = orig_chars[orig_idx].0;
+        for _ in 0..chunk_normalized.len() {
+            offsets.push(orig_byte_start);
+        }
+        norm_consumed += chunk_normalized.len();
+        orig_idx = window_end;
+    }
+    offsets.push(text.len());
+
+    (normalized, offsets)
+}
+
+/// マッチの前後にある空白文字を、フラグに応じて境界に吸収する
+fn apply_strip(text: &str, mut start: usize, mut end: usize, lstrip: bool, rstrip: bool) -> (usize, usize) {
+    if lstrip {
+        while let Some(prev) = text[..start].chars().next_back() {
+            if !prev.is_whitespace() {
+                break;
+            }
+            start -= prev.len_utf8();
+        }
+    }
+    if rstrip {
+        while let Some(next) = text[end..].chars().next() {
+            if !next.is_whitespace() {
+                break;
+            }
+            end += next.len_utf8();
+        }
+    }
+    (start, end)
+}
+
+/// 指定したバイト範囲（`[start, end)`）に重なるトークンの最初と最後のインデックスを返す。
+/// `single_word` がfalseのエントリがトークン境界に揃わずにマッチした場合に、
+/// どの範囲のトークンをマッチが消費するかを決めるために使う。
+fn find_overlapping_token_indices(tokens: &[Token], start: usize, end: usize) -> Option<(usize, usize)> {
+    let first = tokens.iter().position(|t| t.byte_end > start)?;
+    let last = tokens.iter().rposition(|t| t.byte_start < end)?;
+    (first <= last).then_some((first, last))
+}
+
+/// Aho-Corasickオートマトンがヒットしたマッチに、対応するユーザー辞書エントリを結びつけたもの
+struct Candidate<'a> {
+    start: usize,
+    end: usize,
+    entry: &'a UserDictionaryEntry,
+}
+
+/// トークン列を走査し、ユーザー辞書と一致する連続トークンを結合する関数。
+///
+/// 以前は候補文字列をウィンドウごとに毎回連結して `HashSet::contains` で
+/// 照合していたため、辞書中の最長語長に対して計算量が2乗になっていた。
+/// 代わりにユーザー辞書語から一度だけAho-Corasickオートマトンを構築し、
+/// 生テキストを1回スキャンして最左最長マッチを求める。`normalized` なエントリは
+/// 表層形と入力テキストの双方をNFKC正規化した上で別のオートマトンでマッチさせ、
+/// 正規化後のバイト位置を元テキストの位置に逆写像する。
+///
+/// マッチはエントリごとの挙動（`single_word`/`lstrip`/`rstrip`）に従って調整される。
+/// `single_word` なエントリは `Token::byte_start`/`byte_end` から作った
+/// バイトオフセット→トークン番号の対応表を使い、マッチの開始・終了がどちらも
+/// トークン境界と一致する場合にのみ採用する。そうでないエントリは、マッチに
+/// 重なるトークンの範囲を消費し、マッチした範囲だけを結合語とする。重なる
+/// トークンのうちマッチからはみ出した前後の部分は、別のトークンとして出力する。
+pub(crate) fn merge_user_dictionary_words(
+    text: &str,
+    tokens: &[Token],
+    user_dictionary: &[UserDictionaryEntry],
+) -> Result<(Vec<MergedToken>, HashSet<String>)> {
+    let mut extracted_user_dictionaries = HashSet::new();
+
+    if user_dictionary.is_empty() {
+        let corrected_tokens = tokens.iter().map(token_to_merged).collect();
+        return Ok((corrected_tokens, extracted_user_dictionaries));
+    }
+
+    let raw_entries: Vec<&UserDictionaryEntry> =
+        user_dictionary.iter().filter(|e| !e.normalized).collect();
+    let normalized_entries: Vec<&UserDictionaryEntry> =
+        user_dictionary.iter().filter(|e| e.normalized).collect();
+
+    let mut candidates: Vec<Candidate> = Vec::new();
+
+    if !raw_entries.is_empty() {
+        let patterns: Vec<&str> = raw_entries.iter().map(|e| e.surface.as_str()).collect();
+        let automaton = AhoCorasickBuilder::new()
+            .match_kind(MatchKind::LeftmostLongest)
+            .build(&patterns)
+            .with_context(|| "ユーザー辞書からのAho-Corasickオートマトン構築に失敗しました。")?;
+
+        for found in automaton.find_iter(text) {
+            let entry = raw_entries[found.pattern().as_usize()];
+            let (start, end) = apply_strip(text, found.start(), found.end(), entry.lstrip, entry.rstrip);
+            candidates.push(Candidate { start, end, entry });
+        }
+    }
+
+    if !normalized_entries.is_empty() {
+        let (normalized_text, offsets) = normalize_with_offsets(text);
+        let normalized_patterns: Vec<String> = normalized_entries
+            .iter()
+            .map(|e| e.surface.nfkc().collect())
+            .collect();
+        let automaton = AhoCorasickBuilder::new()
+            .match_kind(MatchKind::LeftmostLongest)
+            .build(&normalized_patterns)
+            .with_context(|| "ユーザー辞書からのAho-Corasickオートマトン構築に失敗しました。")?;
+
+        for found in automaton.find_iter(&normalized_text) {
+            let entry = normalized_entries[found.pattern().as_usize()];
+            let orig_start = offsets[found.start()];
+            let orig_end = offsets[found.end()];
+            let (start, end) = apply_strip(text, orig_start, orig_end, entry.lstrip, entry.rstrip);
+            candidates.push(Candidate { start, end, entry });
+        }
+    }
+
+    // 最左最長のマッチを優先するため、開始位置の昇順・同じ開始位置なら長い方を先に並べる
+    candidates.sort_by(|a, b| a.start.cmp(&b.start).then_with(|| b.end.cmp(&a.end)));
+
+    // バイト単位で重ならない候補だけを残す（同じ開始位置なら最長のものを選ぶ）
+    let mut selected: Vec<Candidate> = Vec::new();
+    let mut byte_cursor = 0usize;
+    for candidate in candidates {
+        if candidate.start < byte_cursor {
+            continue;
+        }
+        byte_cursor = candidate.end;
+        selected.push(candidate);
+    }
+
+    // バイトオフセット→トークン番号の対応表（single_wordなエントリの境界判定に使う）
+    let start_index: HashMap<usize, usize> = tokens
+        .iter()
+        .enumerate()
+        .map(|(i, t)| (t.byte_start, i))
+        .collect();
+    let end_index: HashMap<usize, usize> = tokens
+        .iter()
+        .enumerate()
+        .map(|(i, t)| (t.byte_end, i))
+        .collect();
+
+    let mut corrected_tokens = Vec::with_capacity(tokens.len());
+    let mut next_unconsumed = 0usize;
+
+    for candidate in selected {
+        let token_range = if candidate.entry.single_word {
+            match (start_index.get(&candidate.start), end_index.get(&candidate.end)) {
+                (Some(&s), Some(&e)) if s <= e => Some((s, e)),
+                _ => None,
+            }
+        } else {
+            find_overlapping_token_indices(tokens, candidate.start, candidate.end)
+        };
+
+        let (start_idx, end_idx) = match token_range {
+            Some(range) if range.0 >= next_unconsumed => range,
+            // トークン境界に揃わない、または既に消費済みの範囲と重なるマッチは破棄する
+            _ => continue,
+        };
+
+        // マッチ開始前の未カバー区間はそのままのトークンとして出力する
+        for token in &tokens[next_unconsumed..start_idx] {
+            corrected_tokens.push(token_to_merged(token));
+        }
+
+        // single_wordでないエントリはトークン境界からはみ出した部分（重なるトークンの
+        // 前後の余り）を持つことがあるため、マッチの実際の範囲（candidate.start/end）を
+        // 使い、はみ出した部分は別トークンとして欠落しないよう出力する
+        let byte_start = candidate.start;
+        let byte_end = candidate.end;
+        let surface = text[byte_start..byte_end].to_string();
+
+        if !candidate.entry.single_word {
+            let leading_start = tokens[start_idx].byte_start;
+            if leading_start < byte_start {
+                corrected_tokens.push(MergedToken {
+                    text: text[leading_start..byte_start].to_string(),
+                    byte_start: leading_start,
+                    byte_end: byte_start,
+                    position: tokens[start_idx].position,
+                    position_length: 1,
+                    pos: None,
+                    pos_detail: None,
+                    base_form: None,
+                    reading: None,
+                });
+            }
+        }
+
+        corrected_tokens.push(MergedToken {
+            text: surface.clone(),
+            byte_start,
+            byte_end,
+            position: tokens[start_idx].position,
+            position_length: tokens[end_idx].position_length,
+            // 複数トークンにまたがる結合語には単一のLindera品詞情報が存在しないため、
+            // ユーザー辞書由来であることを示す合成の品詞ラベルを付与する
+            pos: Some(CUSTOM_DICTIONARY_POS.to_string()),
+            pos_detail: Some(CUSTOM_DICTIONARY_POS_DETAIL.to_string()),
+            base_form: Some(surface.clone()),
+            reading: None,
+        });
+        extracted_user_dictionaries.insert(surface);
+
+        if !candidate.entry.single_word {
+            let trailing_end = tokens[end_idx].byte_end;
+            if byte_end < trailing_end {
+                corrected_tokens.push(MergedToken {
+                    text: text[byte_end..trailing_end].to_string(),
+                    byte_start: byte_end,
+                    byte_end: trailing_end,
+                    position: tokens[end_idx].position,
+                    position_length: 1,
+                    pos: None,
+                    pos_detail: None,
+                    base_form: None,
+                    reading: None,
+                });
+            }
+        }
+
+        next_unconsumed = end_idx + 1;
+    }
+
+    // 最後のマッチ以降に残った区間はそのままのトークンとして出力する
+    for token in &tokens[next_unconsumed..] {
+        corrected_tokens.push(token_to_merged(token));
+    }
+
+    Ok((corrected_tokens, extracted_user_dictionaries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(text: &str, byte_start: usize, byte_end: usize, position: usize) -> Token {
+        Token {
+            text: text.to_string(),
+            byte_start,
+            byte_end,
+            position,
+            position_length: 1,
+            details: None,
+        }
+    }
+
+    fn entry(surface: &str, single_word: bool) -> UserDictionaryEntry {
+        UserDictionaryEntry {
+            surface: surface.to_string(),
+            single_word,
+            lstrip: false,
+            rstrip: false,
+            normalized: false,
+        }
+    }
+
+    /// single_wordなエントリが複数の連続トークンをまたいで
+    /// Aho-Corasickでマッチした場合に、1つの結合語トークンへ正しく統合されることを確認する。
+    #[test]
+    fn merges_aligned_multi_token_entry() {
+        let text = "東京都に行く";
+        let tokens = vec![
+            token("東京", 0, 6, 0),
+            token("都", 6, 9, 1),
+            token("に", 9, 12, 2),
+            token("行く", 12, 18, 3),
+        ];
+        let dictionary = vec![entry("東京都", true)];
+
+        let (merged, extracted) = merge_user_dictionary_words(text, &tokens, &dictionary).unwrap();
+
+        assert_eq!(merged[0].text, "東京都");
+        assert_eq!(merged[0].byte_start, 0);
+        assert_eq!(merged[0].byte_end, 9);
+        assert_eq!(merged[0].pos.as_deref(), Some(CUSTOM_DICTIONARY_POS));
+        assert!(extracted.contains("東京都"));
+        assert_eq!(merged[1].text, "に");
+        assert_eq!(merged[2].text, "行く");
+    }
+
+    /// single_word=falseのエントリがトークン境界をまたいで途中からマッチする場合、
+    /// マッチ自体は実際の一致範囲だけを結合語とし、はみ出した前後の文字を
+    /// 欠落させずに別トークンとして残すことを確認する（境界に揃わないマッチが
+    /// 重なるトークン全体を飲み込んでしまう回帰の防止）。
+    #[test]
+    fn partial_overlap_keeps_leftover_characters() {
+        let text = "京都庁に行く";
+        let tokens = vec![
+            token("京都", 0, 6, 0),
+            token("庁", 6, 9, 1),
+            token("に", 9, 12, 2),
+            token("行く", 12, 18, 3),
+        ];
+        let dictionary = vec![entry("都庁", false)];
+
+        let (merged, extracted) = merge_user_dictionary_words(text, &tokens, &dictionary).unwrap();
+
+        let texts: Vec<&str> = merged.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(texts, vec!["京", "都庁", "に", "行く"]);
+        assert!(extracted.contains("都庁"));
+
+        let merged_entry = &merged[1];
+        assert_eq!(merged_entry.byte_start, 3);
+        assert_eq!(merged_entry.byte_end, 9);
+    }
+
+    /// lstrip/rstripが有効なエントリは、マッチ前後の空白をトークン境界ごと
+    /// 結合語に吸収することを確認する。
+    #[test]
+    fn lstrip_rstrip_absorbs_surrounding_whitespace() {
+        let text = " 東京 ";
+        let tokens = vec![
+            token(" ", 0, 1, 0),
+            token("東京", 1, 7, 1),
+            token(" ", 7, 8, 2),
+        ];
+        let dictionary = vec![UserDictionaryEntry {
+            surface: "東京".to_string(),
+            single_word: true,
+            lstrip: true,
+            rstrip: true,
+            normalized: false,
+        }];
+
+        let (merged, extracted) = merge_user_dictionary_words(text, &tokens, &dictionary).unwrap();
+
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].text, " 東京 ");
+        assert_eq!(merged[0].byte_start, 0);
+        assert_eq!(merged[0].byte_end, 8);
+        assert!(extracted.contains(" 東京 "));
+    }
+
+    /// normalized=trueのエントリは、NFKC正規化後に一致する半角カナ+濁点のような
+    /// 分解された入力ともマッチすることを確認する（一文字ずつ正規化すると
+    /// 濁点が合成されず見逃していた回帰の防止）。
+    #[test]
+    fn normalized_entry_matches_decomposed_halfwidth_input() {
+        let text = "ｶﾞ";
+        let tokens = vec![token(text, 0, text.len(), 0)];
+        let dictionary = vec![UserDictionaryEntry {
+            surface: "ガ".to_string(),
+            single_word: true,
+            lstrip: false,
+            rstrip: false,
+            normalized: true,
+        }];
+
+        let (merged, extracted) = merge_user_dictionary_words(text, &tokens, &dictionary).unwrap();
+
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].text, "ｶﾞ");
+        assert_eq!(merged[0].pos.as_deref(), Some(CUSTOM_DICTIONARY_POS));
+        assert!(extracted.contains("ｶﾞ"));
+    }
+
+    /// normalize_with_offsetsが半角カナ+濁点の2文字を、合成後の1文字（"ガ"）へ
+    /// 正しくまとめ、そのバイト全体が元テキストの先頭を指すことを確認する。
+    #[test]
+    fn normalize_with_offsets_composes_across_chars() {
+        let text = "ｶﾞ";
+        let (normalized, offsets) = normalize_with_offsets(text);
+
+        assert_eq!(normalized, "ガ");
+        assert_eq!(offsets, vec![0, 0, 0, text.len()]);
+    }
+}
@@ -0,0 +1,189 @@
+use crate::MergedToken;
+use anyhow::{Context, Result};
+use csv::ReaderBuilder;
+use std::collections::HashSet;
+use std::fs::File;
+
+/// トークンフィルタの設定。CLIフラグごとに1フィールドを持ち、
+/// 未指定のものはフィルタを適用しない。
+#[derive(Default)]
+pub struct FilterConfig {
+    pub stop_words: Option<HashSet<String>>,
+    pub min_len: Option<usize>,
+    pub max_len: Option<usize>,
+    pub drop_symbols: bool,
+    pub lowercase_latin: bool,
+    /// 指定された品詞大分類のみを通す（Lindera由来の `pos` フィールドに対するフィルタ）
+    pub pos: Option<HashSet<String>>,
+}
+
+impl FilterConfig {
+    /// いずれかのフィルタが有効かどうか
+    pub fn is_active(&self) -> bool {
+        self.stop_words.is_some()
+            || self.min_len.is_some()
+            || self.max_len.is_some()
+            || self.drop_symbols
+            || self.lowercase_latin
+            || self.pos.is_some()
+    }
+}
+
+/// ストップワードリストを読み込む関数。1行1語のテキストファイルと、
+/// 1行に複数語をカンマ区切りで並べたCSVファイルの両方に対応する
+/// （ヘッダー行は無く、列数が行ごとに異なっても良い）。
+/// 空行・空フィールドと前後の空白は無視する。
+pub fn load_stop_words(path: &str) -> Result<HashSet<String>> {
+    let file = File::open(path)
+        .with_context(|| format!("ストップワードファイル '{}' を開くことができませんでした。", path))?;
+    let mut rdr = ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(file);
+
+    let mut stop_words = HashSet::new();
+    for result in rdr.records() {
+        let record = result.with_context(|| "ストップワードファイルの読み込みに失敗しました。")?;
+        for field in record.iter() {
+            let word = field.trim();
+            if !word.is_empty() {
+                stop_words.insert(word.to_string());
+            }
+        }
+    }
+    Ok(stop_words)
+}
+
+/// トークンの表層形がすべて記号・句読点で構成されているか（英数字や文字を含まないか）を判定する。
+fn is_symbol_only(text: &str) -> bool {
+    !text.chars().any(|c| c.is_alphanumeric())
+}
+
+/// 設定に従ってマージ済みトークン列にフィルタチェーンを適用する関数。
+/// 適用順序は lowercasing → ストップワード → 長さ → 記号除去 → 品詞。
+pub fn apply_filters(tokens: Vec<MergedToken>, config: &FilterConfig) -> Vec<MergedToken> {
+    tokens
+        .into_iter()
+        .map(|mut token| {
+            if config.lowercase_latin {
+                token.text = token.text.to_lowercase();
+            }
+            token
+        })
+        .filter(|token| {
+            config
+                .stop_words
+                .as_ref()
+                .map(|stop_words| !stop_words.contains(&token.text))
+                .unwrap_or(true)
+        })
+        .filter(|token| {
+            let len = token.text.chars().count();
+            config.min_len.map(|min| len >= min).unwrap_or(true)
+                && config.max_len.map(|max| len <= max).unwrap_or(true)
+        })
+        .filter(|token| !(config.drop_symbols && is_symbol_only(&token.text)))
+        .filter(|token| {
+            config
+                .pos
+                .as_ref()
+                .map(|allowed| {
+                    token
+                        .pos
+                        .as_deref()
+                        .map(|pos| allowed.contains(pos))
+                        .unwrap_or(false)
+                })
+                .unwrap_or(true)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn merged(text: &str, pos: Option<&str>) -> MergedToken {
+        MergedToken {
+            text: text.to_string(),
+            byte_start: 0,
+            byte_end: text.len(),
+            position: 0,
+            position_length: 1,
+            pos: pos.map(|p| p.to_string()),
+            pos_detail: None,
+            base_form: None,
+            reading: None,
+        }
+    }
+
+    /// 1行1語のテキスト形式のストップワードファイルを読み込めることを確認する。
+    #[test]
+    fn load_stop_words_reads_line_delimited_file() {
+        let path = std::env::temp_dir().join("filters_test_line_delimited.txt");
+        std::fs::File::create(&path)
+            .unwrap()
+            .write_all("これ\nそれ\n\nあれ\n".as_bytes())
+            .unwrap();
+
+        let stop_words = load_stop_words(path.to_str().unwrap()).unwrap();
+
+        assert!(stop_words.contains("これ"));
+        assert!(stop_words.contains("それ"));
+        assert!(stop_words.contains("あれ"));
+        assert_eq!(stop_words.len(), 3);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// 1行に複数語をカンマ区切りで並べたCSV形式のストップワードファイルも
+    /// 読み込めることを確認する。
+    #[test]
+    fn load_stop_words_reads_csv_file() {
+        let path = std::env::temp_dir().join("filters_test_csv.csv");
+        std::fs::File::create(&path)
+            .unwrap()
+            .write_all("これ,それ\nあれ\n".as_bytes())
+            .unwrap();
+
+        let stop_words = load_stop_words(path.to_str().unwrap()).unwrap();
+
+        assert!(stop_words.contains("これ"));
+        assert!(stop_words.contains("それ"));
+        assert!(stop_words.contains("あれ"));
+        assert_eq!(stop_words.len(), 3);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// フィルタチェーンが小文字化・ストップワード・長さ・品詞の各条件で
+    /// 期待通りトークンを除外することを確認する。
+    #[test]
+    fn apply_filters_chains_all_conditions() {
+        let mut stop_words = HashSet::new();
+        stop_words.insert("the".to_string());
+
+        let config = FilterConfig {
+            stop_words: Some(stop_words),
+            min_len: Some(2),
+            max_len: None,
+            drop_symbols: true,
+            lowercase_latin: true,
+            pos: Some(HashSet::from(["名詞".to_string()])),
+        };
+
+        let tokens = vec![
+            merged("THE", Some("名詞")),
+            merged("cat", Some("名詞")),
+            merged("a", Some("名詞")),
+            merged("、", Some("名詞")),
+            merged("cat", Some("動詞")),
+        ];
+
+        let result = apply_filters(tokens, &config);
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].text, "cat");
+    }
+}
@@ -1,53 +1,264 @@
 use anyhow::{Context, Result};
-use csv::ReaderBuilder;
 use csv::WriterBuilder;
 use lindera_core::mode::Mode;
 use lindera_dictionary::{DictionaryConfig, DictionaryKind};
-use lindera_tokenizer::token::Token;
 use lindera_tokenizer::tokenizer::{Tokenizer, TokenizerConfig};
-use serde::Deserialize;
+use std::collections::HashMap;
 use std::collections::HashSet;
 use std::env;
 use std::fs::File;
 use std::io::{BufReader, BufWriter, Read, Write};
 
-/// ユーザー辞書のCSVレコードを表す構造体
-#[derive(Debug, Deserialize)]
-struct UserDictionaryRecord {
-    #[serde(rename = "UserDictionary")]
-    user_dictionary: String,
-}
+mod dict;
+mod filters;
+mod ngram;
+mod script;
+use filters::FilterConfig;
 
 /// マージされたトークンを表す構造体
-struct MergedToken {
-    text: String,
-    byte_start: usize,
-    byte_end: usize,
-    position: usize,
-    position_length: usize,
+pub(crate) struct MergedToken {
+    pub(crate) text: String,
+    pub(crate) byte_start: usize,
+    pub(crate) byte_end: usize,
+    pub(crate) position: usize,
+    pub(crate) position_length: usize,
+    /// 品詞の大分類（例: 名詞、動詞）。Lindera辞書に詳細情報が無いトークンではNone
+    pub(crate) pos: Option<String>,
+    /// 品詞の細分類（品詞細分類1〜3をスラッシュ区切りで連結したもの）
+    pub(crate) pos_detail: Option<String>,
+    /// 原形（辞書形）
+    pub(crate) base_form: Option<String>,
+    /// 読み（カタカナ）
+    pub(crate) reading: Option<String>,
 }
 
-fn main() -> Result<()> {
-    // コマンドライン引数の取得
-    let args: Vec<String> = env::args().collect();
+/// IPADICの `Token.details` からLinderaの品詞情報を取り出す関数。
+/// IPADICの詳細情報は
+/// [品詞, 品詞細分類1, 品詞細分類2, 品詞細分類3, 活用型, 活用形, 原形, 読み, 発音]
+/// の順で格納されている。
+pub(crate) fn pos_fields_from_details(
+    details: &Option<Vec<String>>,
+) -> (Option<String>, Option<String>, Option<String>, Option<String>) {
+    let details = match details {
+        Some(details) => details,
+        None => return (None, None, None, None),
+    };
+
+    let pos = details.first().cloned();
+    let pos_detail = details
+        .get(1..4)
+        .map(|parts| {
+            parts
+                .iter()
+                .filter(|part| part.as_str() != "*")
+                .cloned()
+                .collect::<Vec<_>>()
+                .join("/")
+        })
+        .filter(|detail| !detail.is_empty());
+    let base_form = details.get(6).cloned().filter(|form| form != "*");
+    let reading = details.get(7).cloned().filter(|reading| reading != "*");
+
+    (pos, pos_detail, base_form, reading)
+}
+
+/// 出力モード（出現順の一覧か、集計済みの頻度表か）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputMode {
+    /// 出現したトークンをそのまま一覧で出力する（従来の挙動）
+    Occurrences,
+    /// 表層形ごとに出現数を集計した頻度表を出力する
+    Frequency,
+}
+
+/// 頻度表の1行分のエントリ
+struct FrequencyEntry {
+    token: String,
+    count: usize,
+    relative_frequency: f64,
+}
+
+/// コマンドライン引数を解析した結果
+struct CliOptions {
+    input_path: String,
+    output_path: String,
+    user_dic_list_path: Option<String>,
+    output_mode: OutputMode,
+    stop_words_path: Option<String>,
+    min_len: Option<usize>,
+    max_len: Option<usize>,
+    drop_symbols: bool,
+    lowercase_latin: bool,
+    pos_filter: Option<HashSet<String>>,
+    show_pos_columns: bool,
+    script_routing: bool,
+    show_script: bool,
+    ngram_range: Option<(usize, usize)>,
+    ngram_edge_only: bool,
+}
+
+/// コマンドライン引数を解析する関数。
+/// 位置引数（入力ファイル・出力ファイル・任意のユーザー辞書）と
+/// `--output-mode` 等のフラグを読み取る。
+fn parse_args(args: &[String]) -> Result<CliOptions> {
+    let mut positional = Vec::new();
+    let mut output_mode = OutputMode::Occurrences;
+    let mut stop_words_path = None;
+    let mut min_len = None;
+    let mut max_len = None;
+    let mut drop_symbols = false;
+    let mut lowercase_latin = false;
+    let mut pos_filter = None;
+    let mut show_pos_columns = false;
+    let mut script_routing = false;
+    let mut show_script = false;
+    let mut ngram_range = None;
+    let mut ngram_edge_only = false;
+
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--output-mode" => {
+                let value = args
+                    .get(i + 1)
+                    .with_context(|| "--output-mode の値が指定されていません。")?;
+                output_mode = match value.as_str() {
+                    "occurrences" => OutputMode::Occurrences,
+                    "frequency" => OutputMode::Frequency,
+                    other => anyhow::bail!("不明な --output-mode の値です: {}", other),
+                };
+                i += 2;
+            }
+            "--stop-words" => {
+                let value = args
+                    .get(i + 1)
+                    .with_context(|| "--stop-words の値が指定されていません。")?;
+                stop_words_path = Some(value.clone());
+                i += 2;
+            }
+            "--min-len" => {
+                let value = args
+                    .get(i + 1)
+                    .with_context(|| "--min-len の値が指定されていません。")?;
+                min_len = Some(
+                    value
+                        .parse()
+                        .with_context(|| format!("--min-len の値が不正です: {}", value))?,
+                );
+                i += 2;
+            }
+            "--max-len" => {
+                let value = args
+                    .get(i + 1)
+                    .with_context(|| "--max-len の値が指定されていません。")?;
+                max_len = Some(
+                    value
+                        .parse()
+                        .with_context(|| format!("--max-len の値が不正です: {}", value))?,
+                );
+                i += 2;
+            }
+            "--drop-symbols" => {
+                drop_symbols = true;
+                i += 1;
+            }
+            "--lowercase-latin" => {
+                lowercase_latin = true;
+                i += 1;
+            }
+            "--pos" => {
+                let value = args
+                    .get(i + 1)
+                    .with_context(|| "--pos の値が指定されていません。")?;
+                pos_filter = Some(value.split(',').map(|s| s.trim().to_string()).collect());
+                i += 2;
+            }
+            "--show-pos-columns" => {
+                show_pos_columns = true;
+                i += 1;
+            }
+            "--script-routing" => {
+                script_routing = true;
+                i += 1;
+            }
+            "--show-script" => {
+                show_script = true;
+                i += 1;
+            }
+            "--ngram" => {
+                let min_value = args
+                    .get(i + 1)
+                    .with_context(|| "--ngram の最小値が指定されていません。")?;
+                let max_value = args
+                    .get(i + 2)
+                    .with_context(|| "--ngram の最大値が指定されていません。")?;
+                let min_n: usize = min_value
+                    .parse()
+                    .with_context(|| format!("--ngram の最小値が不正です: {}", min_value))?;
+                let max_n: usize = max_value
+                    .parse()
+                    .with_context(|| format!("--ngram の最大値が不正です: {}", max_value))?;
+                if min_n == 0 || min_n > max_n {
+                    anyhow::bail!(
+                        "--ngram は 1 <= MIN <= MAX を満たす必要があります（MIN={}, MAX={}）。",
+                        min_n,
+                        max_n
+                    );
+                }
+                ngram_range = Some((min_n, max_n));
+                i += 3;
+            }
+            "--ngram-edge" => {
+                ngram_edge_only = true;
+                i += 1;
+            }
+            other => {
+                positional.push(other.to_string());
+                i += 1;
+            }
+        }
+    }
 
-    // 引数の数をチェック（3または4）
-    if args.len() != 3 && args.len() != 4 {
+    if positional.len() != 2 && positional.len() != 3 {
         eprintln!(
-            "使用方法: {} <入力ファイル.txt> <出力ファイル.csv> [<ユーザー辞書.csv>]",
+            "使用方法: {} <入力ファイル.txt> <出力ファイル.csv> [<ユーザー辞書.csv>] \
+             [--output-mode occurrences|frequency] [--stop-words <ファイル>] \
+             [--min-len <N>] [--max-len <N>] [--drop-symbols] [--lowercase-latin] \
+             [--pos <品詞,品詞,...>] [--show-pos-columns] \
+             [--script-routing] [--show-script] [--ngram <MIN> <MAX>] [--ngram-edge]",
             args[0]
         );
         std::process::exit(1);
     }
 
+    Ok(CliOptions {
+        input_path: positional[0].clone(),
+        output_path: positional[1].clone(),
+        user_dic_list_path: positional.get(2).cloned(),
+        output_mode,
+        stop_words_path,
+        min_len,
+        max_len,
+        drop_symbols,
+        lowercase_latin,
+        pos_filter,
+        show_pos_columns,
+        script_routing,
+        show_script,
+        ngram_range,
+        ngram_edge_only,
+    })
+}
+
+fn main() -> Result<()> {
+    // コマンドライン引数の取得
+    let args: Vec<String> = env::args().collect();
+    let options = parse_args(&args)?;
+
     // 引数のパスを取得
-    let input_path = &args[1];
-    let output_path = &args[2];
-    let user_dic_list_path = if args.len() == 4 {
-        Some(&args[3])
-    } else {
-        None
-    };
+    let input_path = &options.input_path;
+    let output_path = &options.output_path;
+    let user_dic_list_path = options.user_dic_list_path.as_ref();
 
     // 入力ファイルを開く
     let file = File::open(input_path).with_context(|| {
@@ -64,167 +275,205 @@ fn main() -> Result<()> {
         .read_to_string(&mut text)
         .with_context(|| format!("ファイル '{}' の読み込みに失敗しました。", input_path))?;
 
-    // 辞書の設定
-    let dictionary = DictionaryConfig {
-        kind: Some(DictionaryKind::IPADIC),
-        path: None,
-    };
+    // N-gramモードが指定されている場合は、形態素解析器を作らずに文字N-gramへ分割する
+    let corrected_tokens = if let Some((min_n, max_n)) = options.ngram_range {
+        if user_dic_list_path.is_some() || options.script_routing {
+            eprintln!(
+                "警告: --ngram 使用時はユーザー辞書の結合やスクリプトルーティングには対応していないため無視されます。"
+            );
+        }
+        ngram::tokenize_ngrams(&text, min_n, max_n, options.ngram_edge_only)
+    } else {
+        // 辞書の設定
+        let dictionary = DictionaryConfig {
+            kind: Some(DictionaryKind::IPADIC),
+            path: None,
+        };
+
+        // トークナイザーの設定
+        let config = TokenizerConfig {
+            dictionary,
+            user_dictionary: None, // オプションのユーザー辞書は後でマージするため、ここではNoneに設定
+            mode: Mode::Normal,
+        };
+
+        // トークナイザーの作成
+        let tokenizer =
+            Tokenizer::from_config(config).with_context(|| "トークナイザーの作成に失敗しました。")?;
+
+        // スクリプトルーティング（CJKランのみLinderaにかけ、非CJKランは空白・記号で分割する）が
+        // 有効な場合は、通常のLinderaトークン化とユーザー辞書の結合をスキップする
+        if options.script_routing {
+            if user_dic_list_path.is_some() {
+                eprintln!(
+                    "警告: --script-routing 使用時はユーザー辞書の結合に対応していないため、指定されたユーザー辞書は無視されます。"
+                );
+            }
+            script::tokenize_with_script_routing(&text, &tokenizer)?
+        } else {
+            // テキストのトークン化
+            let tokens = tokenizer
+                .tokenize(&text)
+                .with_context(|| "テキストのトークン化に失敗しました。")?;
+
+            // ユーザー辞書の読み込み（オプション）
+            let user_dic = if let Some(path) = user_dic_list_path {
+                Some(
+                    dict::load_user_dictionary(path).with_context(|| {
+                        format!(
+                            "ユーザー辞書 '{}' の読み込みに失敗しました。",
+                            path
+                        )
+                    })?,
+                )
+            } else {
+                None
+            };
 
-    // トークナイザーの設定
-    let config = TokenizerConfig {
-        dictionary,
-        user_dictionary: None, // オプションのユーザー辞書は後でマージするため、ここではNoneに設定
-        mode: Mode::Normal,
+            // ユーザー辞書を使用するかどうかで処理を分岐
+            if let Some(user_dic_set) = &user_dic {
+                // トークンリストの修正（ユーザー辞書の単語の結合）
+                let (merged_tokens, _extracted_user_dictionaries) =
+                    dict::merge_user_dictionary_words(&text, &tokens, user_dic_set)?;
+
+                merged_tokens
+            } else {
+                // ユーザー辞書がない場合は、単純にTokenをMergedTokenに変換
+                tokens
+                    .iter()
+                    .map(|t| {
+                        let (pos, pos_detail, base_form, reading) =
+                            pos_fields_from_details(&t.details);
+                        MergedToken {
+                            text: t.text.to_string().clone(),
+                            byte_start: t.byte_start,
+                            byte_end: t.byte_end,
+                            position: t.position,
+                            position_length: t.position_length,
+                            pos,
+                            pos_detail,
+                            base_form,
+                            reading,
+                        }
+                    })
+                    .collect()
+            }
+        }
     };
 
-    // トークナイザーの作成
-    let tokenizer =
-        Tokenizer::from_config(config).with_context(|| "トークナイザーの作成に失敗しました。")?;
-
-    // テキストのトークン化
-    let tokens = tokenizer
-        .tokenize(&text)
-        .with_context(|| "テキストのトークン化に失敗しました。")?;
-
-    // ユーザー辞書の読み込み（オプション）
-    let user_dic = if let Some(path) = user_dic_list_path {
-        Some(
-            load_user_dictionary(path).with_context(|| {
-                format!(
-                    "ユーザー辞書 '{}' の読み込みに失敗しました。",
-                    path
-                )
-            })?,
-        )
-    } else {
-        None
+    // フィルタ設定を構築し、有効なものがあればトークン列に適用する
+    let filter_config = FilterConfig {
+        stop_words: options
+            .stop_words_path
+            .as_ref()
+            .map(|path| {
+                filters::load_stop_words(path).with_context(|| {
+                    format!("ストップワードファイル '{}' の読み込みに失敗しました。", path)
+                })
+            })
+            .transpose()?,
+        min_len: options.min_len,
+        max_len: options.max_len,
+        drop_symbols: options.drop_symbols,
+        lowercase_latin: options.lowercase_latin,
+        pos: options.pos_filter.clone(),
     };
-
-    // ユーザー辞書を使用するかどうかで処理を分岐
-    let corrected_tokens = if let Some(user_dic_set) = &user_dic {
-        // ユーザー辞書から最大のトークン数を計算
-        let max_user_dic_length = user_dic_set
-            .iter()
-            .map(|name| name.chars().count())
-            .max()
-            .unwrap_or(1);
-
-        // トークンリストの修正（ユーザー辞書の単語の結合）
-        let (merged_tokens, _extracted_user_dictionaries) =
-            merge_user_dictionary_words(&tokens, user_dic_set, max_user_dic_length);
-
-        merged_tokens
+    let corrected_tokens = if filter_config.is_active() {
+        filters::apply_filters(corrected_tokens, &filter_config)
     } else {
-        // ユーザー辞書がない場合は、単純にTokenをMergedTokenに変換
-        tokens
-            .iter()
-            .map(|t| MergedToken {
-                text: t.text.to_string().clone(),
-                byte_start: t.byte_start,
-                byte_end: t.byte_end,
-                position: t.position,
-                position_length: t.position_length,
-            })
-            .collect()
+        corrected_tokens
     };
 
-    // トークンをCSVに書き込む
-    write_tokens_to_csv(output_path, &corrected_tokens)
-        .with_context(|| format!("CSVファイル '{}' の作成に失敗しました。", output_path))?;
+    // 出力モードに応じてCSVに書き込む
+    match options.output_mode {
+        OutputMode::Occurrences => {
+            write_tokens_to_csv(
+                output_path,
+                &corrected_tokens,
+                options.show_pos_columns,
+                options.show_script,
+            )
+            .with_context(|| format!("CSVファイル '{}' の作成に失敗しました。", output_path))?;
+        }
+        OutputMode::Frequency => {
+            let frequency_table = aggregate_frequencies(&corrected_tokens);
+            write_frequency_table_to_csv(output_path, &frequency_table).with_context(|| {
+                format!("CSVファイル '{}' の作成に失敗しました。", output_path)
+            })?;
+        }
+    }
 
     println!("トークン化が完了し、{} に出力されました。", output_path);
 
     Ok(())
 }
 
-/// ユーザー辞書リストをCSVから読み込みHashSetに格納する関数
-fn load_user_dictionary(path: &str) -> Result<HashSet<String>> {
-    let file = File::open(path).with_context(|| {
-        format!(
-            "ユーザー辞書ファイル '{}' を開くことができませんでした。",
-            path
-        )
-    })?;
-    let mut rdr = ReaderBuilder::new().has_headers(true).from_reader(file);
-
-    let mut user_dic_set = HashSet::new();
-    for result in rdr.deserialize() {
-        let record: UserDictionaryRecord =
-            result.with_context(|| "ユーザー辞書のレコードのデシリアライズに失敗しました。")?;
-        user_dic_set.insert(record.user_dictionary.clone());
+/// マージ済みトークンを表層形ごとに集計し、頻度表を作成する関数。
+/// 出現数の降順、同数の場合は表層形の辞書順で安定してソートする。
+fn aggregate_frequencies(tokens: &[MergedToken]) -> Vec<FrequencyEntry> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for token in tokens {
+        *counts.entry(token.text.as_str()).or_insert(0) += 1;
     }
-    Ok(user_dic_set)
+
+    let total = tokens.len() as f64;
+    let mut entries: Vec<FrequencyEntry> = counts
+        .into_iter()
+        .map(|(token, count)| FrequencyEntry {
+            token: token.to_string(),
+            count,
+            relative_frequency: if total > 0.0 {
+                count as f64 / total
+            } else {
+                0.0
+            },
+        })
+        .collect();
+
+    entries.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.token.cmp(&b.token)));
+    entries
 }
 
-/// トークンリストを走査し、ユーザー辞書と一致する連続トークンを結合する関数
-fn merge_user_dictionary_words(
-    tokens: &[Token],
-    user_dictionary: &HashSet<String>,
-    max_length: usize,
-) -> (Vec<MergedToken>, HashSet<String>) {
-    let mut corrected_tokens = Vec::with_capacity(tokens.len());
-    let mut extracted_user_dictionaries = HashSet::new();
-    let mut i = 0;
-    let len = tokens.len();
-
-    while i < len {
-        let mut matched = false;
-
-        // 最大マッチングの長さを設定（ユーザー辞書の最大単語数）
-        let max_match_length = max_length;
-
-        // マッチングを試みる
-        for window_size in (1..=max_match_length).rev() {
-            if i + window_size > len {
-                continue;
-            }
+/// 頻度表をCSVに書き込む関数（BOM付きUTF-8）。
+/// 列は Token, count, relative_frequency, rank の順で、
+/// rank はソート後の順位（1始まり）。
+fn write_frequency_table_to_csv(output_path: &str, entries: &[FrequencyEntry]) -> Result<()> {
+    let file = File::create(output_path)
+        .with_context(|| format!("CSVファイル '{}' を作成できませんでした。", output_path))?;
+    let mut writer = BufWriter::new(file);
 
-            // トークンを連結して候補の単語を生成
-            let candidate: String = tokens[i..i + window_size]
-                .iter()
-                .map(|t| t.text)
-                .collect::<String>();
-
-            if user_dictionary.contains(&candidate) {
-                // 一致する単語が見つかった場合
-                // 新しいマージされたトークンを作成
-                let merged_token = MergedToken {
-                    text: candidate.clone(),
-                    byte_start: tokens[i].byte_start,
-                    byte_end: tokens[i + window_size - 1].byte_end,
-                    position: tokens[i].position,
-                    position_length: tokens[i + window_size - 1].position_length,
-                };
+    writer
+        .write_all(&[0xEF, 0xBB, 0xBF])
+        .with_context(|| "BOMの書き込みに失敗しました。")?;
 
-                corrected_tokens.push(merged_token);
-                extracted_user_dictionaries.insert(candidate.clone());
+    let mut wtr = WriterBuilder::new().has_headers(true).from_writer(writer);
 
-                i += window_size;
-                matched = true;
-                break;
-            }
-        }
+    wtr.write_record(&["Token", "count", "relative_frequency", "rank"])
+        .with_context(|| "CSVヘッダーの書き込みに失敗しました。")?;
 
-        if !matched {
-            // 一致する単語が見つからなかった場合、現在のトークンをそのまま追加
-            let token = &tokens[i];
-            let unmerged_token = MergedToken {
-                text: token.text.to_string().clone(),
-                byte_start: token.byte_start,
-                byte_end: token.byte_end,
-                position: token.position,
-                position_length: token.position_length,
-            };
-            corrected_tokens.push(unmerged_token);
-            i += 1;
-        }
+    for (index, entry) in entries.iter().enumerate() {
+        wtr.write_record(&[
+            &entry.token,
+            &entry.count.to_string(),
+            &format!("{:.6}", entry.relative_frequency),
+            &(index + 1).to_string(),
+        ])
+        .with_context(|| "頻度表の書き込みに失敗しました。")?;
     }
 
-    (corrected_tokens, extracted_user_dictionaries)
+    wtr.flush()
+        .with_context(|| "CSVのフラッシュに失敗しました。")?;
+
+    Ok(())
 }
 
 /// トークンをCSVに書き込む関数（BOM付きUTF-8）
-fn write_tokens_to_csv(output_path: &str, tokens: &[MergedToken]) -> Result<()> {
+fn write_tokens_to_csv(
+    output_path: &str,
+    tokens: &[MergedToken],
+    include_pos_columns: bool,
+    include_script_column: bool,
+) -> Result<()> {
     let file = File::create(output_path)
         .with_context(|| format!("CSVファイル '{}' を作成できませんでした。", output_path))?;
     let mut writer = BufWriter::new(file);
@@ -238,24 +487,39 @@ fn write_tokens_to_csv(output_path: &str, tokens: &[MergedToken]) -> Result<()>
     let mut wtr = WriterBuilder::new().has_headers(true).from_writer(writer);
 
     // CSVのヘッダーを設定
-    wtr.write_record(&[
-        "Token",
-        "byte_start",
-        "byte_end",
-        "position",
-        "position_length",
-    ])
-    .with_context(|| "CSVヘッダーの書き込みに失敗しました。")?;
+    let mut header = vec!["Token", "byte_start", "byte_end", "position", "position_length"];
+    if include_pos_columns {
+        header.extend(["pos", "pos_detail", "base_form", "reading"]);
+    }
+    if include_script_column {
+        header.push("script");
+    }
+    wtr.write_record(&header)
+        .with_context(|| "CSVヘッダーの書き込みに失敗しました。")?;
 
     for token in tokens {
-        wtr.write_record(&[
-            &token.text,
-            &token.byte_start.to_string(),
-            &token.byte_end.to_string(),
-            &token.position.to_string(),
-            &token.position_length.to_string(),
-        ])
-        .with_context(|| "トークンの書き込みに失敗しました。")?;
+        let mut record = vec![
+            token.text.clone(),
+            token.byte_start.to_string(),
+            token.byte_end.to_string(),
+            token.position.to_string(),
+            token.position_length.to_string(),
+        ];
+        if include_pos_columns {
+            record.push(token.pos.clone().unwrap_or_default());
+            record.push(token.pos_detail.clone().unwrap_or_default());
+            record.push(token.base_form.clone().unwrap_or_default());
+            record.push(token.reading.clone().unwrap_or_default());
+        }
+        if include_script_column {
+            record.push(
+                script::dominant_script_label(&token.text)
+                    .unwrap_or_default()
+                    .to_string(),
+            );
+        }
+        wtr.write_record(&record)
+            .with_context(|| "トークンの書き込みに失敗しました。")?;
     }
 
     // CSVをフラッシュ
@@ -264,3 +528,78 @@ fn write_tokens_to_csv(output_path: &str, tokens: &[MergedToken]) -> Result<()>
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merged(text: &str, pos: Option<&str>) -> MergedToken {
+        MergedToken {
+            text: text.to_string(),
+            byte_start: 0,
+            byte_end: text.len(),
+            position: 0,
+            position_length: 1,
+            pos: pos.map(|p| p.to_string()),
+            pos_detail: None,
+            base_form: None,
+            reading: None,
+        }
+    }
+
+    /// 出現数の降順、同数の場合は表層形の辞書順で安定ソートされることを確認する。
+    #[test]
+    fn aggregate_frequencies_sorts_by_count_then_token() {
+        let tokens = vec![
+            merged("猫", None),
+            merged("犬", None),
+            merged("猫", None),
+            merged("鳥", None),
+            merged("犬", None),
+        ];
+
+        let entries = aggregate_frequencies(&tokens);
+
+        assert_eq!(entries[0].token, "犬");
+        assert_eq!(entries[0].count, 2);
+        assert_eq!(entries[1].token, "猫");
+        assert_eq!(entries[1].count, 2);
+        assert_eq!(entries[2].token, "鳥");
+        assert_eq!(entries[2].count, 1);
+        assert!((entries[0].relative_frequency - 0.4).abs() < f64::EPSILON);
+    }
+
+    /// IPADICの品詞詳細配列から、品詞・品詞細分類（"*"を除外して連結）・
+    /// 原形・読みを正しく取り出せることを確認する。
+    #[test]
+    fn pos_fields_from_details_parses_ipadic_order() {
+        let details = Some(vec![
+            "名詞".to_string(),
+            "一般".to_string(),
+            "*".to_string(),
+            "*".to_string(),
+            "*".to_string(),
+            "*".to_string(),
+            "猫".to_string(),
+            "ネコ".to_string(),
+            "ネコ".to_string(),
+        ]);
+
+        let (pos, pos_detail, base_form, reading) = pos_fields_from_details(&details);
+
+        assert_eq!(pos.as_deref(), Some("名詞"));
+        assert_eq!(pos_detail.as_deref(), Some("一般"));
+        assert_eq!(base_form.as_deref(), Some("猫"));
+        assert_eq!(reading.as_deref(), Some("ネコ"));
+    }
+
+    /// detailsがNoneの場合は全フィールドがNoneになることを確認する。
+    #[test]
+    fn pos_fields_from_details_handles_missing_details() {
+        let (pos, pos_detail, base_form, reading) = pos_fields_from_details(&None);
+        assert!(pos.is_none());
+        assert!(pos_detail.is_none());
+        assert!(base_form.is_none());
+        assert!(reading.is_none());
+    }
+}
@@ -0,0 +1,81 @@
+use crate::MergedToken;
+
+/// 文字N-gramトークン化モード。形態素解析の代わりに、`min_n`〜`max_n`文字の
+/// スライド窓をテキストに適用し、各N-gramをMergedTokenとして出力する。
+/// 日本語のように形態素境界が曖昧な言語でも、辞書に依存しない文字単位の
+/// 頻度分析を行えるようにする。
+///
+/// `edge_only` がtrueの場合は、各語（空白で区切られた区間）の先頭を起点とする
+/// 接頭辞N-gramのみを生成する（Elasticsearchの edge_ngram に相当）。
+/// 空白をまたぐN-gramはどちらのモードでも生成しない。
+pub(crate) fn tokenize_ngrams(text: &str, min_n: usize, max_n: usize, edge_only: bool) -> Vec<MergedToken> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let len = chars.len();
+    let mut tokens = Vec::new();
+    let mut position = 0usize;
+
+    for i in 0..len {
+        if chars[i].1.is_whitespace() {
+            continue;
+        }
+        if edge_only {
+            let at_word_start = i == 0 || chars[i - 1].1.is_whitespace();
+            if !at_word_start {
+                continue;
+            }
+        }
+
+        for n in min_n..=max_n {
+            if i + n > len {
+                break;
+            }
+            // 空白をまたぐN-gramはここで打ち切る（nを増やすほど範囲が広がるだけなので以降も打ち切ってよい）
+            if chars[i..i + n].iter().any(|(_, c)| c.is_whitespace()) {
+                break;
+            }
+
+            let byte_start = chars[i].0;
+            let byte_end = if i + n < len { chars[i + n].0 } else { text.len() };
+
+            tokens.push(MergedToken {
+                text: text[byte_start..byte_end].to_string(),
+                byte_start,
+                byte_end,
+                position,
+                position_length: 1,
+                pos: None,
+                pos_detail: None,
+                base_form: None,
+                reading: None,
+            });
+            position += 1;
+        }
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// min_n〜max_nの範囲で文字N-gramを生成する際に、空白をまたぐものと
+    /// 語の末尾を越えるものが生成されないことを確認する。
+    #[test]
+    fn tokenize_ngrams_generates_sliding_windows() {
+        let tokens = tokenize_ngrams("ab cd", 2, 3, false);
+        let texts: Vec<&str> = tokens.iter().map(|t| t.text.as_str()).collect();
+
+        assert_eq!(texts, vec!["ab", "cd"]);
+    }
+
+    /// edge_onlyがtrueの場合は、各語の先頭を起点とする接頭辞N-gramのみが
+    /// 生成されることを確認する。
+    #[test]
+    fn tokenize_ngrams_edge_only_generates_prefixes() {
+        let tokens = tokenize_ngrams("abcd", 1, 3, true);
+        let texts: Vec<&str> = tokens.iter().map(|t| t.text.as_str()).collect();
+
+        assert_eq!(texts, vec!["a", "ab", "abc"]);
+    }
+}
@@ -0,0 +1,289 @@
+use crate::{pos_fields_from_details, MergedToken};
+use anyhow::{Context, Result};
+use lindera_tokenizer::tokenizer::Tokenizer;
+
+/// トークンの表層形から検出したおおまかなUnicodeスクリプト／文字種別
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Script {
+    Han,
+    Hiragana,
+    Katakana,
+    Latin,
+    Cyrillic,
+    Digit,
+    Other,
+}
+
+impl Script {
+    fn label(self) -> &'static str {
+        match self {
+            Script::Han => "Han",
+            Script::Hiragana => "Hiragana",
+            Script::Katakana => "Katakana",
+            Script::Latin => "Latin",
+            Script::Cyrillic => "Cyrillic",
+            Script::Digit => "Digit",
+            Script::Other => "Other",
+        }
+    }
+}
+
+/// 1文字をおおまかなUnicodeスクリプトに分類する関数。
+/// ラテン文字は実際のラテン文字Unicodeレンジに限定し、ギリシャ文字・ハングル・
+/// アラビア文字・ヘブライ文字・タイ文字などそれ以外のアルファベット文字は
+/// `Other` に分類する（誤って`Latin`扱いにしない）。
+fn classify_char(c: char) -> Script {
+    match c {
+        '\u{3040}'..='\u{309F}' => Script::Hiragana,
+        '\u{30A0}'..='\u{30FF}' | '\u{31F0}'..='\u{31FF}' => Script::Katakana,
+        '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' | '\u{F900}'..='\u{FAFF}' => Script::Han,
+        '\u{0400}'..='\u{04FF}' => Script::Cyrillic,
+        c if c.is_ascii_digit() => Script::Digit,
+        '\u{0041}'..='\u{005A}'
+        | '\u{0061}'..='\u{007A}'
+        | '\u{00C0}'..='\u{00FF}'
+        | '\u{0100}'..='\u{017F}'
+        | '\u{0180}'..='\u{024F}' => Script::Latin,
+        _ => Script::Other,
+    }
+}
+
+/// 日本語の形態素解析にかけるべき文字種か（Han/Hiragana/Katakana）を判定する関数
+fn is_cjk(script: Script) -> bool {
+    matches!(script, Script::Han | Script::Hiragana | Script::Katakana)
+}
+
+/// トークンの表層形に最も多く出現する文字種を検出する関数。
+/// 空白・記号など分類不能な文字のみの場合はNoneを返す。
+pub(crate) fn dominant_script_label(text: &str) -> Option<&'static str> {
+    let mut counts: [usize; 6] = [0; 6];
+    let index = |s: Script| -> usize {
+        match s {
+            Script::Han => 0,
+            Script::Hiragana => 1,
+            Script::Katakana => 2,
+            Script::Latin => 3,
+            Script::Cyrillic => 4,
+            Script::Digit => 5,
+            Script::Other => usize::MAX,
+        }
+    };
+
+    for c in text.chars() {
+        let script = classify_char(c);
+        if script == Script::Other {
+            continue;
+        }
+        counts[index(script)] += 1;
+    }
+
+    let scripts = [
+        Script::Han,
+        Script::Hiragana,
+        Script::Katakana,
+        Script::Latin,
+        Script::Cyrillic,
+        Script::Digit,
+    ];
+    scripts
+        .iter()
+        .zip(counts.iter())
+        .filter(|(_, &count)| count > 0)
+        .max_by_key(|(_, &count)| count)
+        .map(|(script, _)| script.label())
+}
+
+/// 同一の文字種分類（CJKかどうか）が連続する区間
+struct ScriptRun {
+    byte_start: usize,
+    byte_end: usize,
+    is_cjk: bool,
+}
+
+/// 入力テキストをCJK（Han/Hiragana/Katakana）とそれ以外の連続区間に分割する関数。
+/// 空白や記号はどちらのクラスにも属さないため、直前の区間クラスに含めて
+/// ランが無用に細切れにならないようにする。
+fn split_runs(text: &str) -> Vec<ScriptRun> {
+    let mut runs = Vec::new();
+    let mut run_start = 0usize;
+    let mut run_is_cjk: Option<bool> = None;
+
+    for (byte_pos, c) in text.char_indices() {
+        let script = classify_char(c);
+        let this_is_cjk = if script == Script::Other {
+            run_is_cjk.unwrap_or(false)
+        } else {
+            is_cjk(script)
+        };
+
+        match run_is_cjk {
+            None => run_is_cjk = Some(this_is_cjk),
+            Some(current) if current != this_is_cjk => {
+                runs.push(ScriptRun {
+                    byte_start: run_start,
+                    byte_end: byte_pos,
+                    is_cjk: current,
+                });
+                run_start = byte_pos;
+                run_is_cjk = Some(this_is_cjk);
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(is_cjk) = run_is_cjk {
+        runs.push(ScriptRun {
+            byte_start: run_start,
+            byte_end: text.len(),
+            is_cjk,
+        });
+    }
+
+    runs
+}
+
+/// 非CJKランを空白・記号で区切って単語トークンを作る関数。
+/// 戻り値は生成したトークン列と、次のランに引き継ぐposition番号。
+fn split_latin_words(
+    run_text: &str,
+    run_byte_start: usize,
+    start_position: usize,
+) -> (Vec<MergedToken>, usize) {
+    let mut words = Vec::new();
+    let mut position = start_position;
+    let mut word_start: Option<usize> = None;
+
+    let push_word = |start: usize, end: usize, position: usize, words: &mut Vec<MergedToken>| {
+        words.push(MergedToken {
+            text: run_text[start..end].to_string(),
+            byte_start: run_byte_start + start,
+            byte_end: run_byte_start + end,
+            position,
+            position_length: 1,
+            pos: None,
+            pos_detail: None,
+            base_form: None,
+            reading: None,
+        });
+    };
+
+    for (i, c) in run_text.char_indices() {
+        if c.is_alphanumeric() {
+            if word_start.is_none() {
+                word_start = Some(i);
+            }
+        } else if let Some(start) = word_start.take() {
+            push_word(start, i, position, &mut words);
+            position += 1;
+        }
+    }
+    if let Some(start) = word_start {
+        push_word(start, run_text.len(), position, &mut words);
+        position += 1;
+    }
+
+    (words, position)
+}
+
+/// スクリプト検出によって入力をCJKランと非CJKランに分割し、CJKランだけを
+/// Linderaの形態素解析にかけ、非CJKランは空白・記号区切りの単語分割にかける。
+/// 複数言語が混在する文書でLinderaが英単語を誤って分割してしまう問題に対応する。
+///
+/// 制約: この経路はユーザー辞書の結合には対応していない
+/// （呼び出し側で警告を出し、結合をスキップすること）。
+pub(crate) fn tokenize_with_script_routing(
+    text: &str,
+    tokenizer: &Tokenizer,
+) -> Result<Vec<MergedToken>> {
+    let mut result = Vec::new();
+    let mut position_offset = 0usize;
+
+    for run in split_runs(text) {
+        let run_text = &text[run.byte_start..run.byte_end];
+        if run_text.is_empty() {
+            continue;
+        }
+
+        if run.is_cjk {
+            let tokens = tokenizer
+                .tokenize(run_text)
+                .with_context(|| "CJKランのトークン化に失敗しました。")?;
+
+            let mut max_position_end = position_offset;
+            for t in &tokens {
+                let (pos, pos_detail, base_form, reading) = pos_fields_from_details(&t.details);
+                let position = position_offset + t.position;
+                max_position_end = max_position_end.max(position + t.position_length);
+                result.push(MergedToken {
+                    text: t.text.to_string(),
+                    byte_start: run.byte_start + t.byte_start,
+                    byte_end: run.byte_start + t.byte_end,
+                    position,
+                    position_length: t.position_length,
+                    pos,
+                    pos_detail,
+                    base_form,
+                    reading,
+                });
+            }
+            position_offset = max_position_end;
+        } else {
+            let (words, next_position) =
+                split_latin_words(run_text, run.byte_start, position_offset);
+            result.extend(words);
+            position_offset = next_position;
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 日本語と英単語が混在するテキストが、CJKランと非CJKランに正しく分割されることを確認する。
+    #[test]
+    fn split_runs_separates_cjk_and_latin() {
+        let runs = split_runs("東京はTokyoです");
+        let texts: Vec<&str> = runs
+            .iter()
+            .map(|r| &"東京はTokyoです"[r.byte_start..r.byte_end])
+            .collect();
+
+        assert_eq!(texts, vec!["東京は", "Tokyo", "です"]);
+        assert!(runs[0].is_cjk);
+        assert!(!runs[1].is_cjk);
+        assert!(runs[2].is_cjk);
+    }
+
+    /// 英単語ランが空白・記号区切りで単語分割され、位置番号が連番で振られることを確認する。
+    #[test]
+    fn split_latin_words_splits_on_whitespace_and_punctuation() {
+        let (words, next_position) = split_latin_words("hello, world!", 0, 0);
+
+        let texts: Vec<&str> = words.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(texts, vec!["hello", "world"]);
+        assert_eq!(words[0].position, 0);
+        assert_eq!(words[1].position, 1);
+        assert_eq!(next_position, 2);
+    }
+
+    /// ギリシャ文字・ハングルなど非ラテンのアルファベット文字がLatinに
+    /// 誤分類されないことを確認する（回帰防止）。
+    #[test]
+    fn classify_char_does_not_mislabel_other_alphabets_as_latin() {
+        assert_eq!(classify_char('α'), Script::Other);
+        assert_eq!(classify_char('한'), Script::Other);
+        assert_eq!(classify_char('A'), Script::Latin);
+        assert_eq!(classify_char('é'), Script::Latin);
+    }
+
+    /// 表層形中で最も多く出現する文字種がdominant_script_labelで検出できることを確認する。
+    #[test]
+    fn dominant_script_label_picks_majority_script() {
+        assert_eq!(dominant_script_label("東京"), Some("Han"));
+        assert_eq!(dominant_script_label("Tokyo"), Some("Latin"));
+        assert_eq!(dominant_script_label("、。"), None);
+    }
+}